@@ -1,8 +1,15 @@
 //! ANIMATR Desktop Application - Tauri Backend
 
 use serde::{Deserialize, Serialize};
-use std::process::Command;
-use tauri::Manager;
+use std::collections::HashMap;
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
 
 /// Project information returned from Python backend
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,140 +31,423 @@ pub struct RenderStatus {
     pub error_message: Option<String>,
 }
 
-/// Result of a Python command execution
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CommandResult {
-    pub success: bool,
-    pub output: String,
-    pub error: Option<String>,
+/// Errors a command can surface to the frontend, in place of an opaque
+/// `String`, so the UI can branch on what actually went wrong (e.g. show
+/// an install prompt for [`AnimatrError::PythonNotInstalled`] instead of a
+/// generic failure toast).
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum AnimatrError {
+    #[error("python was not found on PATH")]
+    PythonNotInstalled,
+    #[error("command failed with exit code {code:?}: {stderr}")]
+    CommandFailed { code: Option<i32>, stderr: String },
+    #[error("failed to parse command output: {0}")]
+    ParseError(String),
+    #[error("no render job found with id {id}")]
+    NotFound { id: i64 },
+}
+
+impl Serialize for AnimatrError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let kind = match self {
+            AnimatrError::PythonNotInstalled => "pythonNotInstalled",
+            AnimatrError::CommandFailed { .. } => "commandFailed",
+            AnimatrError::ParseError(_) => "parseError",
+            AnimatrError::NotFound { .. } => "notFound",
+        };
+
+        let mut state = serializer.serialize_struct("AnimatrError", 2)?;
+        state.serialize_field("kind", kind)?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// A render subprocess, launched either as the bundled sidecar binary or,
+/// in development, a system `python` on PATH. `cancel_render` only needs
+/// to be able to kill whichever kind is running.
+enum ManagedChild {
+    System(Child),
+    Sidecar(CommandChild),
 }
 
-/// Execute a Python ANIMATR command
-fn run_python_command(args: &[&str]) -> CommandResult {
+impl ManagedChild {
+    /// Kill the subprocess, consuming the handle. Returns whether the
+    /// kill was actually issued successfully.
+    fn kill(self) -> bool {
+        match self {
+            ManagedChild::System(mut child) => child.start_kill().is_ok(),
+            ManagedChild::Sidecar(child) => child.kill().is_ok(),
+        }
+    }
+}
+
+/// Handles to in-flight render subprocesses, keyed by job id, so that
+/// `cancel_render` can actually terminate a hung render instead of just
+/// asking the Python CLI to mark it cancelled.
+#[derive(Default)]
+pub struct AppState {
+    jobs: Mutex<HashMap<i64, ManagedChild>>,
+}
+
+/// Whether to shell out to a system `python` on PATH instead of the
+/// bundled sidecar binary. Release builds always use the sidecar; debug
+/// builds default to system Python (so contributors don't need a built
+/// sidecar to iterate) unless `ANIMATR_SIDECAR` is set.
+fn use_system_python() -> bool {
+    cfg!(debug_assertions) && std::env::var("ANIMATR_SIDECAR").is_err()
+}
+
+/// Execute a Python ANIMATR command without blocking the async runtime.
+async fn run_python_command(app: &AppHandle, args: &[&str]) -> Result<String, AnimatrError> {
+    if use_system_python() {
+        run_via_system_python(args).await
+    } else {
+        run_via_sidecar(app, args).await
+    }
+}
+
+async fn run_via_system_python(args: &[&str]) -> Result<String, AnimatrError> {
     let output = Command::new("python")
         .arg("-m")
         .arg("animatr")
         .args(args)
-        .output();
-
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        .output()
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => AnimatrError::PythonNotInstalled,
+            _ => AnimatrError::CommandFailed {
+                code: None,
+                stderr: e.to_string(),
+            },
+        })?;
 
-            CommandResult {
-                success: output.status.success(),
-                output: stdout,
-                error: if stderr.is_empty() { None } else { Some(stderr) },
-            }
-        }
-        Err(e) => CommandResult {
-            success: false,
-            output: String::new(),
-            error: Some(e.to_string()),
-        },
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(AnimatrError::CommandFailed {
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
     }
 }
 
-/// List all projects
-#[tauri::command]
-async fn list_projects() -> Result<Vec<ProjectInfo>, String> {
-    let result = run_python_command(&["list", "--json"]);
+async fn run_via_sidecar(app: &AppHandle, args: &[&str]) -> Result<String, AnimatrError> {
+    let sidecar = app.shell().sidecar("animatr").map_err(|e| AnimatrError::CommandFailed {
+        code: None,
+        stderr: e.to_string(),
+    })?;
 
-    if result.success {
-        serde_json::from_str(&result.output)
-            .map_err(|e| format!("Failed to parse projects: {}", e))
+    let output = sidecar
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| AnimatrError::CommandFailed {
+            code: None,
+            stderr: e.to_string(),
+        })?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
     } else {
-        Err(result.error.unwrap_or_else(|| "Unknown error".to_string()))
+        Err(AnimatrError::CommandFailed {
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
     }
 }
 
-/// Create a new project
+/// List all projects
 #[tauri::command]
-async fn create_project(name: String, description: String) -> Result<ProjectInfo, String> {
-    let result = run_python_command(&["new", &name, "--description", &description, "--json"]);
+async fn list_projects(app: AppHandle) -> Result<Vec<ProjectInfo>, AnimatrError> {
+    let output = run_python_command(&app, &["list", "--json"]).await?;
+    serde_json::from_str(&output).map_err(|e| AnimatrError::ParseError(e.to_string()))
+}
 
-    if result.success {
-        serde_json::from_str(&result.output)
-            .map_err(|e| format!("Failed to parse project: {}", e))
-    } else {
-        Err(result.error.unwrap_or_else(|| "Failed to create project".to_string()))
-    }
+/// Create a new project
+#[tauri::command]
+async fn create_project(
+    app: AppHandle,
+    name: String,
+    description: String,
+) -> Result<ProjectInfo, AnimatrError> {
+    let output =
+        run_python_command(&app, &["new", &name, "--description", &description, "--json"])
+            .await?;
+    serde_json::from_str(&output).map_err(|e| AnimatrError::ParseError(e.to_string()))
 }
 
 /// Validate a YAML spec file
 #[tauri::command]
-async fn validate_spec(path: String) -> Result<bool, String> {
-    let result = run_python_command(&["validate", &path]);
-    Ok(result.success)
+async fn validate_spec(app: AppHandle, path: String) -> Result<bool, AnimatrError> {
+    match run_python_command(&app, &["validate", &path]).await {
+        Ok(_) => Ok(true),
+        Err(AnimatrError::CommandFailed { .. }) => Ok(false),
+        Err(e) => Err(e),
+    }
 }
 
-/// Start rendering a project
+/// Start rendering a project, streaming per-scene progress to the frontend
+/// via `render-progress` events instead of requiring it to poll
+/// `get_render_status`.
+///
+/// The Python CLI is expected to print one JSON `RenderStatus` line per
+/// progress update. Each line is forwarded as it arrives, and a terminal
+/// `render-complete` or `render-failed` event is emitted once the process
+/// exits. A render stopped by `cancel_render`, or replaced by a newer
+/// `start_render` for the same project, emits no terminal event.
 #[tauri::command]
-async fn start_render(project_id: i64) -> Result<RenderStatus, String> {
+async fn start_render(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    project_id: i64,
+) -> Result<(), AnimatrError> {
     let id_str = project_id.to_string();
-    let result = run_python_command(&["render", "--project-id", &id_str, "--json"]);
+    let render_args = ["render", "--project-id", id_str.as_str(), "--json"];
 
-    if result.success {
-        serde_json::from_str(&result.output)
-            .map_err(|e| format!("Failed to parse render status: {}", e))
-    } else {
-        Err(result.error.unwrap_or_else(|| "Failed to start render".to_string()))
+    // The job id mirrors the project id until the Python CLI starts
+    // minting its own, so `cancel_render` can look the process up by the
+    // same id the frontend already tracks.
+    let job_id = project_id;
+    let app_handle = app.clone();
+
+    // A render for this project is already in flight: kill it rather than
+    // silently overwriting its handle below, which would leak an
+    // untrackable, uncancellable orphaned process.
+    if let Some(existing) = state.jobs.lock().await.remove(&job_id) {
+        existing.kill();
     }
-}
 
-/// Get render status
-#[tauri::command]
-async fn get_render_status(job_id: i64) -> Result<RenderStatus, String> {
-    let id_str = job_id.to_string();
-    let result = run_python_command(&["status", "--job-id", &id_str, "--json"]);
+    if use_system_python() {
+        let mut child = Command::new("python")
+            .arg("-m")
+            .arg("animatr")
+            .args(render_args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => AnimatrError::PythonNotInstalled,
+                _ => AnimatrError::CommandFailed {
+                    code: None,
+                    stderr: e.to_string(),
+                },
+            })?;
+
+        let stdout = child.stdout.take().ok_or_else(|| AnimatrError::CommandFailed {
+            code: None,
+            stderr: "failed to capture render stdout".to_string(),
+        })?;
+        let stderr = child.stderr.take().ok_or_else(|| AnimatrError::CommandFailed {
+            code: None,
+            stderr: "failed to capture render stderr".to_string(),
+        })?;
 
-    if result.success {
-        serde_json::from_str(&result.output)
-            .map_err(|e| format!("Failed to parse status: {}", e))
+        let pid = child.id();
+        state
+            .jobs
+            .lock()
+            .await
+            .insert(job_id, ManagedChild::System(child));
+
+        // The stderr pipe must be drained even though we only care about
+        // stdout: once its OS pipe buffer fills, the child blocks on its
+        // own write() and the render hangs forever.
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                eprintln!("render stderr: {}", line);
+            }
+        });
+
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                handle_render_line(&app_handle, &line);
+            }
+
+            let state = app_handle.state::<AppState>();
+            let tracked = {
+                let mut jobs = state.jobs.lock().await;
+                // Only take the entry if it is still this render's own child:
+                // a later start_render may have replaced it under the same id.
+                let own = matches!(jobs.get(&job_id), Some(ManagedChild::System(c)) if c.id() == pid);
+                if own { jobs.remove(&job_id) } else { None }
+            };
+            match tracked {
+                Some(ManagedChild::System(mut child)) => match child.wait().await {
+                    Ok(status) if status.success() => {
+                        let _ = app_handle.emit("render-complete", project_id);
+                    }
+                    Ok(status) => {
+                        let _ = app_handle.emit(
+                            "render-failed",
+                            format!("Render exited with status {}", status),
+                        );
+                    }
+                    Err(e) => {
+                        let _ = app_handle.emit("render-failed", e.to_string());
+                    }
+                },
+                // Job was already removed (e.g. cancel_render killed it),
+                // replaced by a newer render, or is somehow the wrong variant.
+                _ => {}
+            }
+        });
     } else {
-        Err(result.error.unwrap_or_else(|| "Failed to get status".to_string()))
+        let sidecar = app.shell().sidecar("animatr").map_err(|e| AnimatrError::CommandFailed {
+            code: None,
+            stderr: e.to_string(),
+        })?;
+        let (mut rx, child) = sidecar.args(render_args).spawn().map_err(|e| AnimatrError::CommandFailed {
+            code: None,
+            stderr: e.to_string(),
+        })?;
+
+        let pid = child.pid();
+        state
+            .jobs
+            .lock()
+            .await
+            .insert(job_id, ManagedChild::Sidecar(child));
+
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                match event {
+                    CommandEvent::Stdout(bytes) => {
+                        handle_render_line(&app_handle, &String::from_utf8_lossy(&bytes));
+                    }
+                    CommandEvent::Stderr(bytes) => {
+                        eprintln!("render stderr: {}", String::from_utf8_lossy(&bytes));
+                    }
+                    CommandEvent::Error(err) => {
+                        let _ = app_handle.emit("render-failed", err);
+                    }
+                    CommandEvent::Terminated(payload) => {
+                        let state = app_handle.state::<AppState>();
+                        let was_tracked = {
+                            let mut jobs = state.jobs.lock().await;
+                            let own = matches!(jobs.get(&job_id), Some(ManagedChild::Sidecar(c)) if c.pid() == pid);
+                            own && jobs.remove(&job_id).is_some()
+                        };
+                        // If the job is no longer tracked, cancel_render (or
+                        // a newer start_render for the same id) already
+                        // removed it and killed the process — don't emit a
+                        // spurious render-failed for the SIGKILL exit that
+                        // naturally follows a cancel.
+                        if was_tracked {
+                            if payload.code == Some(0) {
+                                let _ = app_handle.emit("render-complete", project_id);
+                            } else {
+                                let _ = app_handle.emit(
+                                    "render-failed",
+                                    format!("Render exited with status {:?}", payload.code),
+                                );
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
     }
+
+    Ok(())
 }
 
-/// Cancel a render job
+/// Parse and forward one line of render progress, logging (rather than
+/// failing the render) if the Python CLI emits a line that isn't valid
+/// `RenderStatus` JSON.
+fn handle_render_line(app: &AppHandle, line: &str) {
+    match serde_json::from_str::<RenderStatus>(line) {
+        Ok(status) => {
+            let _ = app.emit("render-progress", &status);
+        }
+        Err(e) => {
+            eprintln!("Failed to parse render progress line: {}", e);
+        }
+    }
+}
+
+/// Get render status
+///
+/// Kept for compatibility with callers that still poll; the primary path
+/// for tracking an in-flight render is now the `render-progress` event
+/// emitted by [`start_render`].
 #[tauri::command]
-async fn cancel_render(job_id: i64) -> Result<bool, String> {
+async fn get_render_status(app: AppHandle, job_id: i64) -> Result<RenderStatus, AnimatrError> {
     let id_str = job_id.to_string();
-    let result = run_python_command(&["cancel", "--job-id", &id_str]);
-    Ok(result.success)
+    let output = run_python_command(&app, &["status", "--job-id", &id_str, "--json"]).await?;
+    serde_json::from_str(&output).map_err(|e| AnimatrError::ParseError(e.to_string()))
 }
 
-/// Generate AI script from prompt
+/// Cancel a render job, killing the tracked subprocess if one is still
+/// running. Returns whether a live process was actually terminated.
+///
+/// BEHAVIOR CHANGE: this now fails with [`AnimatrError::NotFound`] if
+/// `job_id` isn't a render this session is tracking (already finished, or
+/// never started) — previously this resolved to `Ok(false)` per the
+/// original "returns whether a live process was actually terminated"
+/// contract. Frontend `invoke("cancel_render", ...)` call sites need to
+/// handle the rejected-promise case for an unknown/finished job instead of
+/// only branching on the resolved boolean.
 #[tauri::command]
-async fn generate_from_prompt(prompt: String) -> Result<String, String> {
-    let result = run_python_command(&["ai", "generate", "--prompt", &prompt, "--json"]);
+async fn cancel_render(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    job_id: i64,
+) -> Result<bool, AnimatrError> {
+    let child = state
+        .jobs
+        .lock()
+        .await
+        .remove(&job_id)
+        .ok_or(AnimatrError::NotFound { id: job_id })?;
+    let killed = child.kill();
 
-    if result.success {
-        Ok(result.output)
-    } else {
-        Err(result.error.unwrap_or_else(|| "Failed to generate".to_string()))
+    let id_str = job_id.to_string();
+    match run_python_command(&app, &["cancel", "--job-id", &id_str]).await {
+        Ok(_) | Err(AnimatrError::CommandFailed { .. }) => Ok(killed),
+        Err(e) => Err(e),
     }
 }
 
-/// Check system dependencies
+/// Generate AI script from prompt
 #[tauri::command]
-async fn check_dependencies() -> Result<serde_json::Value, String> {
-    let result = run_python_command(&["doctor", "--json"]);
+async fn generate_from_prompt(app: AppHandle, prompt: String) -> Result<String, AnimatrError> {
+    run_python_command(&app, &["ai", "generate", "--prompt", &prompt, "--json"]).await
+}
 
-    if result.success {
-        serde_json::from_str(&result.output)
-            .map_err(|e| format!("Failed to parse dependencies: {}", e))
-    } else {
+/// Check system dependencies, including which Python runtime
+/// (bundled sidecar or system PATH) the app is currently configured to use.
+#[tauri::command]
+async fn check_dependencies(app: AppHandle) -> Result<serde_json::Value, AnimatrError> {
+    let mut value = match run_python_command(&app, &["doctor", "--json"]).await {
+        Ok(output) => {
+            serde_json::from_str(&output).map_err(|e| AnimatrError::ParseError(e.to_string()))?
+        }
         // Return partial info even on failure
-        Ok(serde_json::json!({
+        Err(e) => serde_json::json!({
             "python": true,
             "moho": false,
             "blender": false,
             "ffmpeg": false,
-            "error": result.error
-        }))
+            "error": e.to_string()
+        }),
+    };
+
+    if let Some(obj) = value.as_object_mut() {
+        let runtime = if use_system_python() { "system" } else { "sidecar" };
+        obj.insert("pythonRuntime".to_string(), serde_json::json!(runtime));
     }
+
+    Ok(value)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -166,6 +456,7 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
+        .manage(AppState::default())
         .invoke_handler(tauri::generate_handler![
             list_projects,
             create_project,